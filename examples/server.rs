@@ -1,4 +1,23 @@
 use simple_server::{Method, Server, StatusCode};
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use steam_auth::{ExpectedState, NonceStore};
+#[cfg(feature = "reqwest")]
+use steam_auth::{ReqwestClient, Verifier};
+
+/// A minimal in-memory nonce store, good enough for a single-process example. A real deployment
+/// should use something that survives a restart and expires old entries, e.g. a database table
+/// keyed on `nonce` with a TTL derived from `issued_at`.
+#[derive(Default)]
+struct InMemoryNonceStore(Mutex<HashSet<String>>);
+
+impl NonceStore for InMemoryNonceStore {
+    fn check_and_insert(&self, nonce: &str, _issued_at: SystemTime) -> bool {
+        self.0.lock().unwrap().insert(nonce.to_owned())
+    }
+}
 
 fn main() {
     let host = "127.0.0.1";
@@ -7,9 +26,10 @@ fn main() {
     println!("Starting server on localhost:8080");
 
     let redirector = steam_auth::Redirector::new("http://localhost:8080", "/callback").unwrap();
+    let nonce_store = InMemoryNonceStore::default();
 
-    #[cfg(feature = "reqwest-09x")]
-    let client = reqwest::Client::new();
+    #[cfg(feature = "reqwest")]
+    let client = ReqwestClient::new();
 
     let server = Server::new(move |request, mut response| {
         match (request.method(), request.uri().path()) {
@@ -30,24 +50,26 @@ fn main() {
                 // Parse query string data into auth_resp
                 let qs = request.uri().query().unwrap();
 
-                // Check with the steam servers if the response was valid
-                #[cfg(feature = "reqwest-09x")]
-                match steam_auth::Verifier::make_verify_request(&client, qs) {
+                // Check with the steam servers if the response was valid. `simple_server`'s
+                // handler isn't async, so we block on the future here; a framework with an async
+                // handler wouldn't need this.
+                #[cfg(feature = "reqwest")]
+                match futures::executor::block_on(Verifier::verify(
+                    &client,
+                    qs,
+                    redirector.return_to(),
+                    &nonce_store,
+                    ExpectedState::None,
+                )) {
                     Ok(id) => Ok(response.body(format!("<h1>Success</h1><p>Steam ID: {}</p>", id).as_bytes().to_vec())?),
                     Err(e) => Ok(response.body(format!("<h1>Error</h1><p>Description: {}</p>", dbg!(e)).as_bytes().to_vec())?),
                 }
 
-                #[cfg(not(feature = "reqwest-09x"))]
+                #[cfg(not(feature = "reqwest"))]
                 {
-                    // TODO: Example usage of the API without reqwest
-                    /*
-                    let (req, verifier) = Verifier::from_querystring(qs).unwrap();
-                    // send off req, get back response
-                    match verifier.verify_response(response.body()) {
-                        Ok(steam_id) => (), // got steam id
-                        Err(e) => (), // Auth failure
-                    }
-                    */
+                    // TODO: Example usage of the API without reqwest - implement `HttpClient` for
+                    // your own client and call `Verifier::verify`, or drive
+                    // `Verifier::from_querystring`/`Verifier::verify_response` directly.
                     unimplemented!();
                 }
             }
@@ -0,0 +1,63 @@
+//! An abstraction over the HTTP client [`Verifier::verify`](crate::Verifier::verify) uses to
+//! send the `check_authentication` request to Steam, so the crate isn't tied to any one async HTTP
+//! stack.
+
+use crate::Error;
+
+/// An async HTTP backend capable of POSTing a form-encoded body.
+///
+/// Implement this for whatever HTTP client you're already using, or enable the `reqwest` feature
+/// to use the provided [`ReqwestClient`].
+#[async_trait::async_trait]
+pub trait HttpClient {
+    /// POSTs `body` (already `application/x-www-form-urlencoded`) to `url`, returning the response
+    /// body as text.
+    async fn post_form(&self, url: &str, body: Vec<u8>) -> Result<String, Error>;
+
+    /// GETs `url` with `query` appended as query parameters, returning the response body as text.
+    /// Used by the `webapi` feature's [`WebApiClient`](crate::WebApiClient) so it can reuse
+    /// whatever [`HttpClient`] the caller already passes to [`Verifier::verify`](crate::Verifier::verify).
+    async fn get(&self, url: &str, query: &[(&str, &str)]) -> Result<String, Error>;
+}
+
+/// A [`HttpClient`] backed by [`reqwest::Client`]. Requires the `reqwest` feature.
+#[cfg(feature = "reqwest")]
+#[derive(Debug, Clone, Default)]
+pub struct ReqwestClient(reqwest::Client);
+
+#[cfg(feature = "reqwest")]
+impl ReqwestClient {
+    /// Constructs a client using `reqwest`'s defaults.
+    pub fn new() -> Self {
+        Self(reqwest::Client::new())
+    }
+}
+
+#[cfg(feature = "reqwest")]
+#[async_trait::async_trait]
+impl HttpClient for ReqwestClient {
+    async fn post_form(&self, url: &str, body: Vec<u8>) -> Result<String, Error> {
+        self.0
+            .post(url)
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .body(body)
+            .send()
+            .await
+            .map_err(Error::Reqwest)?
+            .text()
+            .await
+            .map_err(Error::Reqwest)
+    }
+
+    async fn get(&self, url: &str, query: &[(&str, &str)]) -> Result<String, Error> {
+        self.0
+            .get(url)
+            .query(query)
+            .send()
+            .await
+            .map_err(Error::Reqwest)?
+            .text()
+            .await
+            .map_err(Error::Reqwest)
+    }
+}
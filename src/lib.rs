@@ -2,13 +2,20 @@
 //!
 //! ## Usage
 //!
-//! The easiest way to use this crate is with the `reqwest-09x` feature which allows the library to
-//! make HTTP requests on your behalf. Otherwise, you will need to do that manually.
+//! The easiest way to use this crate is with the `reqwest` feature, which provides a
+//! [`ReqwestClient`] implementation of [`HttpClient`] so the library can make HTTP requests on
+//! your behalf. Otherwise, implement [`HttpClient`] yourself for whatever async HTTP client you're
+//! already using (`surf`, `ureq`, `isahc`, a mock for tests, ...).
 //!
-//! Using the `reqwest-09x` feature:
+//! Using the `reqwest` feature:
 //! ```rust
-//! # use steam_auth::{Redirector, Verifier};
-//! # fn main() {
+//! # use steam_auth::{ExpectedState, NonceStore, Redirector, ReqwestClient, Verifier};
+//! # use std::time::SystemTime;
+//! # struct NoopNonceStore;
+//! # impl NonceStore for NoopNonceStore {
+//! #     fn check_and_insert(&self, _nonce: &str, _issued_at: SystemTime) -> bool { true }
+//! # }
+//! # async fn run() {
 //! // First, create a redirector
 //! let redirector = Redirector::new("http://localhost:8080", "/callback").unwrap();
 //!
@@ -18,77 +25,90 @@
 //!
 //! // Once they've finished authenticating, they will be returned to `/callback` with some data in
 //! // the query string that needs to be parsed and then verified by sending an HTTP request to the steam
-//! // servers.
+//! // servers. Keep `redirector` (or at least its `return_to()`) and a `NonceStore` around from the
+//! // `/login` step so the callback can be tied back to it.
 //! # let querystring = "openid.ns=http%3A%2F%2Fspecs.openid.net%2Fauth%2F2.0&openid.mode=id_res&openid.op_endpoint=https%3A%2F%2Fsteamcommunity.com%2Fopenid%2Flogin&openid.claimed_id=https%3A%2F%2Fsteamcommunity.com%2Fopenid%2Fid%2F92345666790633291&openid.identity=https%3A%2F%2Fsteamcommunity.com%2Fopenid%2Fid%2F12333456789000000&openid.return_to=http%3A%2F%2Flocalhost%3A8080%2Fcallback&openid.response_nonce=2019-06-15T00%3A36%3A00Z7nVIS5lDAcZe%2FT0gT4%2BQNQyexyA%3D&openid.assoc_handle=1234567890&openid.signed=signed%2Cop_endpoint%2Cclaimed_id%2Cidentity%2Creturn_to%2Cresponse_nonce%2Cassoc_handle&openid.sig=BK0zC%2F%2FKzERs7N%2BNlDO0aL06%2BBA%3D";
-//! match Verifier::make_verify_request(&reqwest::Client::new(), querystring) {
+//! # let nonce_store = NoopNonceStore;
+//! let client = ReqwestClient::new();
+//! match Verifier::verify(
+//!     &client,
+//!     querystring,
+//!     redirector.return_to(),
+//!     &nonce_store,
+//!     ExpectedState::None,
+//! )
+//! .await
+//! {
 //!     Ok(steam_id) => println!("Successfully logged in user with steam ID 64 {}", steam_id),
 //!     Err(e) => eprintln!("There was an error authenticating: {}", e),
 //! }
 //! # }
 //! ```
 //!
-//! There is also an asynchronous variant: `Verifier::make_verify_request_async` which returns a
-//! future.
+//! If you don't want to depend on reqwest, implement [`HttpClient`] yourself and call
+//! [`Verifier::verify`] the same way, or call [`Verifier::from_querystring`] /
+//! [`Verifier::verify_response`] directly to drive the HTTP request with your own plumbing. See
+//! the [example server](https://github.com/64/steam-auth/blob/master/examples/server.rs) and the
+//! `Verifier` documentation for more details.
 //!
-//! If you don't want to depend on request, you'll need to send the HTTP request yourself. See the
-//! [example server](https://github.com/64/steam-auth/blob/master/examples/server.rs) and the
-//! `Verifier` documentation for more details on how this can be done.
+//! Once you have a [`SteamId`], the `webapi` feature adds a [`WebApiClient`] for resolving vanity
+//! URLs and fetching public profile information, so you can greet a user by name right after they
+//! log in. See the `WebApiClient` documentation for details.
 
 #[macro_use]
 extern crate serde_derive;
-#[macro_use]
-extern crate failure;
 
+mod http_client;
+mod nonce;
 mod redirector;
+mod state;
+mod steam_id;
 mod verifier;
+#[cfg(feature = "webapi")]
+mod webapi;
 
+pub use http_client::HttpClient;
+#[cfg(feature = "reqwest")]
+pub use http_client::ReqwestClient;
+pub use nonce::NonceStore;
 pub use redirector::Redirector;
-pub use verifier::Verifier;
+pub use steam_id::{AccountType, SteamId, Universe};
+pub use verifier::{ExpectedState, Verifier};
+#[cfg(feature = "webapi")]
+pub use webapi::{OnlineState, PlayerSummary, WebApiClient};
 
 pub(crate) const STEAM_URL: &str = "https://steamcommunity.com/openid/login";
 
-#[derive(Debug, Fail)]
+#[derive(Debug, thiserror::Error)]
 pub enum Error {
-    #[fail(display = "bad site or return url: {}", _0)]
+    #[error("bad site or return url: {0}")]
     /// The site or return URL was incorrect
-    BadUrl(url::ParseError),
-    #[fail(display = "failed to parse SteamAuthRequest (please file bug): {}", _0)]
+    BadUrl(#[source] url::ParseError),
+    #[error("failed to parse SteamAuthRequest (please file bug): {0}")]
     /// Internal error serializing the query string - should never happen.
-    ParseQueryString(serde_urlencoded::ser::Error),
-    #[fail(display = "authentication failed")]
+    ParseQueryString(#[source] serde_urlencoded::ser::Error),
+    #[error("authentication failed")]
     /// The authentication failed because the data provided to the callback was invalid
     AuthenticationFailed,
-    #[fail(display = "failed to parse steam id")]
+    #[error("failed to parse steam id")]
     /// There was an error parsing the Steam ID returned to the callback
     ParseSteamId,
-    #[fail(display = "failed to build HTTP request or response: {}", _0)]
-    BuildHttpStruct(http::Error),
-    #[fail(display = "error serializing url encoded data: {}", _0)]
-    Serialize(serde_urlencoded::ser::Error),
-    #[fail(display = "error deserializing url encoded data: {}", _0)]
-    Deserialize(serde_urlencoded::de::Error),
-    #[fail(display = "reqwest error: {}", _0)]
-    #[cfg(feature = "reqwest-09x")]
+    #[error("state parameter was missing, did not match, or failed signature verification")]
+    /// The CSRF/state token embedded in the return-to URL was missing, didn't match what was
+    /// expected, or (for the HMAC-signed variant) failed verification
+    InvalidState,
+    #[error("failed to build HTTP request or response: {0}")]
+    BuildHttpStruct(#[source] http::Error),
+    #[error("error serializing url encoded data: {0}")]
+    Serialize(#[source] serde_urlencoded::ser::Error),
+    #[error("error deserializing url encoded data: {0}")]
+    Deserialize(#[source] serde_urlencoded::de::Error),
+    #[error("reqwest error: {0}")]
+    #[cfg(feature = "reqwest")]
     /// There was an error during the verify request
-    Reqwest(reqwest::Error),
-}
-
-#[cfg(feature = "reqwest-0_9")]
-pub fn verify_response_async(
-    client: &reqwest::r#async::Client,
-    mut form: SteamAuthResponse,
-) -> impl futures::Future<Item = u64, Error = Error> {
-    client
-        .post(STEAM_URL)
-        .form(&form)
-        .send()
-        .map_err(Error::Reqwest)
-        .and_then(|res| res.into_body().concat2().map_err(Error::Reqwest))
-        .and_then(move |body| {
-            let s = std::str::from_utf8(&body)
-                .map_err(|_| Error::AuthenticationFailed)?
-                .to_owned();
-
-            parse_verify_response(&form.claimed_id, s)
-        })
+    Reqwest(#[source] reqwest::Error),
+    #[error("error deserializing JSON response from the Steam Web API: {0}")]
+    #[cfg(feature = "webapi")]
+    /// The Steam Web API returned a response this crate couldn't parse.
+    DeserializeJson(#[source] serde_json::Error),
 }
@@ -0,0 +1,127 @@
+//! Replay protection for the `response_nonce` Steam attaches to each OpenID response.
+
+use std::time::{Duration, SystemTime};
+
+/// Tracks nonces that have already been consumed so that a given Steam login response can only
+/// be used once.
+///
+/// Implementations should reject (return `false` for) a nonce they have already seen, and may use
+/// `issued_at` to expire old entries so the store doesn't grow unbounded.
+pub trait NonceStore {
+    /// Records `nonce` as used if it hasn't been seen before. Returns `true` if the nonce was
+    /// fresh (and is now recorded), or `false` if it had already been consumed.
+    fn check_and_insert(&self, nonce: &str, issued_at: SystemTime) -> bool;
+}
+
+/// Parses the `YYYY-MM-DDThh:mm:ssZ` timestamp Steam prefixes onto `response_nonce`.
+pub(crate) fn parse_nonce_timestamp(nonce: &str) -> Option<SystemTime> {
+    let prefix = nonce.get(0..20)?;
+    let bytes = prefix.as_bytes();
+
+    if bytes[4] != b'-'
+        || bytes[7] != b'-'
+        || bytes[10] != b'T'
+        || bytes[13] != b':'
+        || bytes[16] != b':'
+        || bytes[19] != b'Z'
+    {
+        return None;
+    }
+
+    let year: i64 = prefix.get(0..4)?.parse().ok()?;
+    let month: u32 = prefix.get(5..7)?.parse().ok()?;
+    let day: u32 = prefix.get(8..10)?.parse().ok()?;
+    let hour: u64 = prefix.get(11..13)?.parse().ok()?;
+    let minute: u64 = prefix.get(14..16)?.parse().ok()?;
+    let second: u64 = prefix.get(17..19)?.parse().ok()?;
+
+    let days = days_from_civil(year, month, day)?;
+    let secs = (days * 86_400) as u64 + hour * 3_600 + minute * 60 + second;
+
+    Some(SystemTime::UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+/// Howard Hinnant's `days_from_civil` algorithm: days since the Unix epoch for a proleptic
+/// Gregorian date, without pulling in a full date/time crate.
+fn days_from_civil(y: i64, m: u32, d: u32) -> Option<i64> {
+    if m < 1 || m > 12 || d < 1 || d > 31 {
+        return None;
+    }
+
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = if y >= 0 { y } else { y - 399 } / 400;
+    let yoe = y - era * 400; // [0, 399]
+    let m = i64::from(m);
+    let d = i64::from(d);
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + d - 1; // [0, 365]
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy; // [0, 146096]
+
+    Some(era * 146_097 + doe - 719_468)
+}
+
+/// Formats `t` back into the `YYYY-MM-DDThh:mm:ssZ` prefix [`parse_nonce_timestamp`] understands,
+/// so tests can build a `response_nonce` for an arbitrary point in time. The inverse of
+/// `days_from_civil`, also from Howard Hinnant's algorithm.
+#[cfg(test)]
+pub(crate) fn format_nonce_timestamp_for_test(t: SystemTime, suffix: &str) -> String {
+    let secs = t.duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs();
+    let (days, rem) = (secs / 86_400, secs % 86_400);
+
+    let z = days as i64 + 719_468;
+    let era = if z >= 0 { z } else { z - 146_096 } / 146_097;
+    let doe = (z - era * 146_097) as u64;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let y = yoe as i64 + era * 400;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = if m <= 2 { y + 1 } else { y };
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z{}",
+        y,
+        m,
+        d,
+        rem / 3_600,
+        (rem % 3_600) / 60,
+        rem % 60,
+        suffix
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_unix_epoch() {
+        let t = parse_nonce_timestamp("1970-01-01T00:00:00Zrest").unwrap();
+        assert_eq!(t, SystemTime::UNIX_EPOCH);
+    }
+
+    #[test]
+    fn parses_known_timestamp() {
+        // 2019-06-15T00:36:00Z is 1560558960 seconds after the Unix epoch.
+        let t = parse_nonce_timestamp("2019-06-15T00:36:00Z7nVIS5lDAcZe/T0gT4+QNQyexyA=").unwrap();
+        assert_eq!(
+            t.duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs(),
+            1_560_558_960
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_prefix() {
+        assert!(parse_nonce_timestamp("not-a-timestamp-at-all").is_none());
+        assert!(parse_nonce_timestamp("2019-06-15 00:36:00Zrest").is_none());
+        assert!(parse_nonce_timestamp("short").is_none());
+    }
+
+    #[test]
+    fn format_and_parse_round_trip() {
+        let t = SystemTime::UNIX_EPOCH + Duration::from_secs(1_560_558_960);
+        let nonce = format_nonce_timestamp_for_test(t, "abc");
+        assert_eq!(nonce, "2019-06-15T00:36:00Zabc");
+        assert_eq!(parse_nonce_timestamp(&nonce).unwrap(), t);
+    }
+}
@@ -1,10 +1,12 @@
-use crate::{Error, STEAM_URL};
+use crate::{state, Error, STEAM_URL};
 use url::Url;
 
 #[derive(Debug, Clone)]
 /// Stores the URL that users should be redirected to.
 pub struct Redirector {
     url: Url,
+    return_to: String,
+    state: Option<String>,
 }
 
 impl Redirector {
@@ -16,11 +18,48 @@ impl Redirector {
     /// # }
     /// ```
     pub fn new<T: AsRef<str>, U: AsRef<str>>(site_url: T, return_url: U) -> Result<Self, Error> {
-        let joined = Url::parse(site_url.as_ref())
+        Self::build(site_url, return_url, None)
+    }
+
+    /// Like [`Redirector::new`], but embeds `state` as an extra `state` query parameter on the
+    /// return-to URL. Store the same value in the user's session and pass it as
+    /// [`ExpectedState::Plain`](crate::ExpectedState::Plain) to
+    /// [`Verifier::from_querystring`](crate::Verifier::from_querystring), which compares it against
+    /// the `state` returned to the callback, protecting the login against CSRF.
+    pub fn new_with_state<T: AsRef<str>, U: AsRef<str>, V: Into<String>>(
+        site_url: T,
+        return_url: U,
+        state: V,
+    ) -> Result<Self, Error> {
+        Self::build(site_url, return_url, Some(state.into()))
+    }
+
+    /// Like [`Redirector::new_with_state`], but generates a random state value and signs it with
+    /// `secret` using HMAC-SHA256, so the callback can be verified as tamper-free without storing
+    /// anything server-side. Pass the same `secret` to
+    /// [`ExpectedState::Signed`](crate::ExpectedState::Signed) when verifying.
+    pub fn new_with_signed_state<T: AsRef<str>, U: AsRef<str>>(
+        site_url: T,
+        return_url: U,
+        secret: &[u8],
+    ) -> Result<Self, Error> {
+        Self::build(site_url, return_url, Some(state::new_signed_state(secret)))
+    }
+
+    fn build<T: AsRef<str>, U: AsRef<str>>(
+        site_url: T,
+        return_url: U,
+        state: Option<String>,
+    ) -> Result<Self, Error> {
+        let mut joined = Url::parse(site_url.as_ref())
             .map_err(Error::BadUrl)?
             .join(return_url.as_ref())
             .map_err(Error::BadUrl)?;
 
+        if let Some(state) = &state {
+            joined.query_pairs_mut().append_pair("state", state);
+        }
+
         let openid = SteamAuthRequest::new(site_url.as_ref(), joined.as_str());
 
         let qs = serde_urlencoded::to_string(&openid).map_err(Error::ParseQueryString)?;
@@ -30,7 +69,11 @@ impl Redirector {
 
         url.set_query(Some(&qs));
 
-        Ok(Self { url })
+        Ok(Self {
+            url,
+            return_to: joined.to_string(),
+            state,
+        })
     }
 
     /// Constructs a new HTTP response which redirects the user to the URL, starting the login
@@ -47,6 +90,19 @@ impl Redirector {
     pub fn url(&self) -> &Url {
         &self.url
     }
+
+    /// Gets the `return_to` URL that the callback is expected to come back to. Pass this to
+    /// [`Verifier::from_querystring`](crate::Verifier::from_querystring) so it can be checked
+    /// against the value Steam actually returns.
+    pub fn return_to(&self) -> &str {
+        &self.return_to
+    }
+
+    /// Gets the opaque `state` value embedded in the return-to URL, if one was set via
+    /// [`Redirector::new_with_state`] or [`Redirector::new_with_signed_state`].
+    pub fn state(&self) -> Option<&str> {
+        self.state.as_deref()
+    }
 }
 
 #[derive(Serialize)]
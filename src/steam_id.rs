@@ -0,0 +1,354 @@
+//! A strongly-typed 64-bit Steam ID, along with its legacy text representations.
+//!
+//! The layout of the 64-bit value (from least to most significant bits) is:
+//!
+//! | Bits  | Field          |
+//! |-------|----------------|
+//! | 0-31  | Account number |
+//! | 32-51 | Instance       |
+//! | 52-55 | Account type   |
+//! | 56-63 | Universe       |
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::Error;
+
+/// The "universe" a [`SteamId`] belongs to (bits 56-63).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Universe {
+    Invalid,
+    Public,
+    Beta,
+    Internal,
+    Dev,
+    Rc,
+    /// A universe value not recognised by this crate.
+    Unknown(u8),
+}
+
+impl Universe {
+    fn from_u8(n: u8) -> Self {
+        match n {
+            0 => Universe::Invalid,
+            1 => Universe::Public,
+            2 => Universe::Beta,
+            3 => Universe::Internal,
+            4 => Universe::Dev,
+            5 => Universe::Rc,
+            n => Universe::Unknown(n),
+        }
+    }
+
+    fn as_u8(self) -> u8 {
+        match self {
+            Universe::Invalid => 0,
+            Universe::Public => 1,
+            Universe::Beta => 2,
+            Universe::Internal => 3,
+            Universe::Dev => 4,
+            Universe::Rc => 5,
+            Universe::Unknown(n) => n,
+        }
+    }
+
+    /// The `X` digit used in the legacy `STEAM_X:Y:Z` representation. Steam clients have always
+    /// hard-coded this to `0` for the public universe (rather than using its real value of `1`),
+    /// so we match that quirk here and reverse it in [`SteamId::from_str`].
+    fn steam2_digit(self) -> u8 {
+        match self {
+            Universe::Public => 0,
+            other => other.as_u8(),
+        }
+    }
+
+    /// The inverse of [`Universe::steam2_digit`].
+    fn from_steam2_digit(n: u8) -> Self {
+        match n {
+            0 => Universe::Public,
+            n => Universe::from_u8(n),
+        }
+    }
+}
+
+/// The "account type" of a [`SteamId`] (bits 52-55).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AccountType {
+    Invalid,
+    Individual,
+    Multiseat,
+    GameServer,
+    AnonGameServer,
+    Pending,
+    ContentServer,
+    Clan,
+    Chat,
+    ConsoleUser,
+    AnonUser,
+    /// An account type value not recognised by this crate.
+    Unknown(u8),
+}
+
+impl AccountType {
+    /// The inverse of [`AccountType::steam3_char`].
+    fn from_steam3_char(c: char) -> Self {
+        match c {
+            'I' => AccountType::Invalid,
+            'U' => AccountType::Individual,
+            'M' => AccountType::Multiseat,
+            'G' => AccountType::GameServer,
+            'A' => AccountType::AnonGameServer,
+            'P' => AccountType::Pending,
+            'C' => AccountType::ContentServer,
+            'g' => AccountType::Clan,
+            'T' | 'L' | 'c' => AccountType::Chat,
+            'a' => AccountType::AnonUser,
+            _ => AccountType::Invalid,
+        }
+    }
+}
+
+impl AccountType {
+    fn from_u8(n: u8) -> Self {
+        match n {
+            0 => AccountType::Invalid,
+            1 => AccountType::Individual,
+            2 => AccountType::Multiseat,
+            3 => AccountType::GameServer,
+            4 => AccountType::AnonGameServer,
+            5 => AccountType::Pending,
+            6 => AccountType::ContentServer,
+            7 => AccountType::Clan,
+            8 => AccountType::Chat,
+            9 => AccountType::ConsoleUser,
+            10 => AccountType::AnonUser,
+            n => AccountType::Unknown(n),
+        }
+    }
+
+    fn as_u8(self) -> u8 {
+        match self {
+            AccountType::Invalid => 0,
+            AccountType::Individual => 1,
+            AccountType::Multiseat => 2,
+            AccountType::GameServer => 3,
+            AccountType::AnonGameServer => 4,
+            AccountType::Pending => 5,
+            AccountType::ContentServer => 6,
+            AccountType::Clan => 7,
+            AccountType::Chat => 8,
+            AccountType::ConsoleUser => 9,
+            AccountType::AnonUser => 10,
+            AccountType::Unknown(n) => n,
+        }
+    }
+
+    /// The single-character code used in the SteamID3 (`[X:1:...]`) representation.
+    fn steam3_char(self) -> char {
+        match self {
+            AccountType::Invalid => 'I',
+            AccountType::Individual => 'U',
+            AccountType::Multiseat => 'M',
+            AccountType::GameServer => 'G',
+            AccountType::AnonGameServer => 'A',
+            AccountType::Pending => 'P',
+            AccountType::ContentServer => 'C',
+            AccountType::Clan => 'g',
+            AccountType::Chat => 'T',
+            AccountType::ConsoleUser => 'a',
+            AccountType::AnonUser => 'a',
+            AccountType::Unknown(_) => 'i',
+        }
+    }
+}
+
+/// A Steam ID, wrapping the 64-bit value Steam uses internally.
+///
+/// # Example
+/// ```
+/// # use steam_auth::SteamId;
+/// let id = SteamId::from_u64(76561197960287930);
+/// assert_eq!(id.account_id(), 22202);
+/// assert_eq!(id.to_steam2(), "STEAM_0:0:11101");
+/// assert_eq!(id.to_steam3(), "[U:1:22202]");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SteamId(u64);
+
+impl SteamId {
+    /// Wraps a raw 64-bit Steam ID.
+    pub fn from_u64(id: u64) -> Self {
+        Self(id)
+    }
+
+    /// Returns the raw 64-bit value.
+    pub fn as_u64(self) -> u64 {
+        self.0
+    }
+
+    /// The account number, bits 0-31.
+    pub fn account_id(self) -> u32 {
+        (self.0 & 0xFFFF_FFFF) as u32
+    }
+
+    /// The instance, bits 32-51.
+    pub fn instance(self) -> u32 {
+        ((self.0 >> 32) & 0x000F_FFFF) as u32
+    }
+
+    /// The account type, bits 52-55.
+    pub fn account_type(self) -> AccountType {
+        AccountType::from_u8(((self.0 >> 52) & 0xF) as u8)
+    }
+
+    /// The universe the account belongs to, bits 56-63.
+    pub fn universe(self) -> Universe {
+        Universe::from_u8(((self.0 >> 56) & 0xFF) as u8)
+    }
+
+    /// Renders the classic `STEAM_X:Y:Z` representation.
+    pub fn to_steam2(self) -> String {
+        let account_number = self.account_id();
+        let y = account_number & 1;
+        let z = account_number >> 1;
+
+        format!("STEAM_{}:{}:{}", self.universe().steam2_digit(), y, z)
+    }
+
+    /// Renders the `[T:U:N]` SteamID3 representation: account type character, universe value and
+    /// account number.
+    pub fn to_steam3(self) -> String {
+        format!(
+            "[{}:{}:{}]",
+            self.account_type().steam3_char(),
+            self.universe().as_u8(),
+            self.account_id()
+        )
+    }
+}
+
+impl fmt::Display for SteamId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for SteamId {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(rest) = s.strip_prefix("STEAM_") {
+            let mut parts = rest.splitn(3, ':');
+            let x: u8 = parts
+                .next()
+                .ok_or(Error::ParseSteamId)?
+                .parse()
+                .map_err(|_| Error::ParseSteamId)?;
+            let y: u32 = parts
+                .next()
+                .ok_or(Error::ParseSteamId)?
+                .parse()
+                .map_err(|_| Error::ParseSteamId)?;
+            let z: u32 = parts
+                .next()
+                .ok_or(Error::ParseSteamId)?
+                .parse()
+                .map_err(|_| Error::ParseSteamId)?;
+
+            let account_number = (z << 1) | y;
+            let universe = Universe::from_steam2_digit(x);
+
+            Ok(SteamId::from_u64(
+                u64::from(account_number)
+                    | (1 << 32) // default instance
+                    | (u64::from(AccountType::Individual.as_u8()) << 52)
+                    | (u64::from(universe.as_u8()) << 56),
+            ))
+        } else if s.starts_with('[') {
+            let trimmed = s.trim_start_matches('[').trim_end_matches(']');
+            let mut parts = trimmed.splitn(3, ':');
+            let account_type = parts
+                .next()
+                .and_then(|s| s.chars().next())
+                .map(AccountType::from_steam3_char)
+                .ok_or(Error::ParseSteamId)?;
+            let universe: u8 = parts
+                .next()
+                .ok_or(Error::ParseSteamId)?
+                .parse()
+                .map_err(|_| Error::ParseSteamId)?;
+            let account_number: u32 = parts
+                .next()
+                .ok_or(Error::ParseSteamId)?
+                .parse()
+                .map_err(|_| Error::ParseSteamId)?;
+
+            Ok(SteamId::from_u64(
+                u64::from(account_number)
+                    | (1 << 32)
+                    | (u64::from(account_type.as_u8()) << 52)
+                    | (u64::from(universe) << 56),
+            ))
+        } else {
+            s.parse::<u64>()
+                .map(SteamId::from_u64)
+                .map_err(|_| Error::ParseSteamId)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GABE_NEWELL: u64 = 76561197960287930;
+
+    #[test]
+    fn unpacks_known_id() {
+        let id = SteamId::from_u64(GABE_NEWELL);
+        assert_eq!(id.account_id(), 22202);
+        assert_eq!(id.instance(), 1);
+        assert_eq!(id.account_type(), AccountType::Individual);
+        assert_eq!(id.universe(), Universe::Public);
+    }
+
+    #[test]
+    fn renders_steam2_and_steam3() {
+        let id = SteamId::from_u64(GABE_NEWELL);
+        assert_eq!(id.to_steam2(), "STEAM_0:0:11101");
+        assert_eq!(id.to_steam3(), "[U:1:22202]");
+    }
+
+    #[test]
+    fn round_trips_steam2() {
+        let id: SteamId = "STEAM_0:0:11101".parse().unwrap();
+        assert_eq!(id.account_id(), 22202);
+        assert_eq!(id.universe(), Universe::Public);
+        assert_eq!(id.account_type(), AccountType::Individual);
+        assert_eq!(id.to_steam2(), "STEAM_0:0:11101");
+    }
+
+    #[test]
+    fn round_trips_steam3_for_non_individual_accounts() {
+        // A clan ('g') in the public universe.
+        let id: SteamId = "[g:1:4]".parse().unwrap();
+        assert_eq!(id.account_id(), 4);
+        assert_eq!(id.universe(), Universe::Public);
+        assert_eq!(id.account_type(), AccountType::Clan);
+        assert_eq!(id.to_steam3(), "[g:1:4]");
+    }
+
+    #[test]
+    fn round_trips_steam3_for_non_public_universe() {
+        let id: SteamId = "[U:2:22202]".parse().unwrap();
+        assert_eq!(id.universe(), Universe::Beta);
+        assert_eq!(id.account_id(), 22202);
+        assert_eq!(id.to_steam3(), "[U:2:22202]");
+    }
+
+    #[test]
+    fn parses_plain_u64() {
+        let id: SteamId = GABE_NEWELL.to_string().parse().unwrap();
+        assert_eq!(id.as_u64(), GABE_NEWELL);
+    }
+}
@@ -1,21 +1,69 @@
-use crate::{Error, STEAM_URL};
+use std::time::{Duration, SystemTime};
 
-#[cfg(feature = "reqwest-09x")]
-use futures::{
-    future::{self, Either},
-    Future, Stream,
-};
+use crate::{nonce::parse_nonce_timestamp, state, Error, HttpClient, NonceStore, SteamId, STEAM_URL};
+
+/// The allowed clock skew between Steam's `response_nonce` timestamp and our own clock, before a
+/// response is rejected as a likely replay.
+const ALLOWED_CLOCK_SKEW: Duration = Duration::from_secs(5 * 60);
+
+/// How [`Verifier::from_querystring`] should check the `state` value embedded by [`Redirector`]
+/// in the return-to URL, if at all.
+///
+/// [`Redirector`]: crate::Redirector
+#[derive(Debug, Clone, Copy)]
+pub enum ExpectedState<'a> {
+    /// Don't check the `state` parameter. Appropriate if you didn't use
+    /// [`Redirector::new_with_state`](crate::Redirector::new_with_state) or
+    /// [`Redirector::new_with_signed_state`](crate::Redirector::new_with_signed_state).
+    None,
+    /// Require `state` to exactly equal a value you stored yourself, e.g. in the user's session,
+    /// when the [`Redirector`] was created with
+    /// [`Redirector::new_with_state`](crate::Redirector::new_with_state).
+    Plain(&'a str),
+    /// Recompute and constant-time-compare the HMAC tag embedded by
+    /// [`Redirector::new_with_signed_state`](crate::Redirector::new_with_signed_state), keyed with
+    /// the same `secret`.
+    Signed(&'a [u8]),
+}
 
 #[derive(Debug, Clone)]
 /// Verifies the login details returned after users have gone through the 'sign in with Steam' page
 /// # Example
 /// ```
-/// # use steam_auth::Verifier;
+/// # use steam_auth::{ExpectedState, NonceStore, Verifier};
+/// # use std::time::{SystemTime, UNIX_EPOCH};
 /// # struct Response; impl Response { fn new() -> Self { Self } fn body(&self) -> &'static
 /// # str { "foo" } }
+/// # struct NoopNonceStore;
+/// # impl NonceStore for NoopNonceStore {
+/// #     fn check_and_insert(&self, _nonce: &str, _issued_at: SystemTime) -> bool { true }
+/// # }
 /// # fn main() {
-/// # let qs = "openid.ns=http%3A%2F%2Fspecs.openid.net%2Fauth%2F2.0&openid.mode=id_res&openid.op_endpoint=https%3A%2F%2Fsteamcommunity.com%2Fopenid%2Flogin&openid.claimed_id=https%3A%2F%2Fsteamcommunity.com%2Fopenid%2Fid%2F92345666790633291&openid.identity=https%3A%2F%2Fsteamcommunity.com%2Fopenid%2Fid%2F12333456789000000&openid.return_to=http%3A%2F%2Flocalhost%3A8080%2Fcallback&openid.response_nonce=2019-06-15T00%3A36%3A00Z7nVIS5lDAcZe%2FT0gT4%2BQNQyexyA%3D&openid.assoc_handle=1234567890&openid.signed=signed%2Cop_endpoint%2Cclaimed_id%2Cidentity%2Creturn_to%2Cresponse_nonce%2Cassoc_handle&openid.sig=BK0zC%2F%2FKzERs7N%2BNlDO0aL06%2BBA%3D";
-/// let (req, verifier) = Verifier::from_querystring(qs).unwrap();
+/// # // `response_nonce` is only accepted within a few minutes of "now" (see `ALLOWED_CLOCK_SKEW`),
+/// # // so this example builds one from the current time instead of a fixed, ever-staler constant.
+/// # fn current_response_nonce() -> String {
+/// #     let secs = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+/// #     let (days, rem) = (secs / 86_400, secs % 86_400);
+/// #     let z = days as i64 + 719_468;
+/// #     let era = if z >= 0 { z } else { z - 146_096 } / 146_097;
+/// #     let doe = (z - era * 146_097) as u64;
+/// #     let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+/// #     let y = yoe as i64 + era * 400;
+/// #     let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+/// #     let mp = (5 * doy + 2) / 153;
+/// #     let d = doy - (153 * mp + 2) / 5 + 1;
+/// #     let m = if mp < 10 { mp + 3 } else { mp - 9 };
+/// #     let y = if m <= 2 { y + 1 } else { y };
+/// #     format!(
+/// #         "{:04}-{:02}-{:02}T{:02}%3A{:02}%3A{:02}Z7nVIS5lDAcZe%2FT0gT4%2BQNQyexyA%3D",
+/// #         y, m, d, rem / 3_600, (rem % 3_600) / 60, rem % 60
+/// #     )
+/// # }
+/// # let qs = format!("openid.ns=http%3A%2F%2Fspecs.openid.net%2Fauth%2F2.0&openid.mode=id_res&openid.op_endpoint=https%3A%2F%2Fsteamcommunity.com%2Fopenid%2Flogin&openid.claimed_id=https%3A%2F%2Fsteamcommunity.com%2Fopenid%2Fid%2F92345666790633291&openid.identity=https%3A%2F%2Fsteamcommunity.com%2Fopenid%2Fid%2F12333456789000000&openid.return_to=http%3A%2F%2Flocalhost%3A8080%2Fcallback&openid.response_nonce={}&openid.assoc_handle=1234567890&openid.signed=signed%2Cop_endpoint%2Cclaimed_id%2Cidentity%2Creturn_to%2Cresponse_nonce%2Cassoc_handle&openid.sig=BK0zC%2F%2FKzERs7N%2BNlDO0aL06%2BBA%3D", current_response_nonce());
+/// # let expected_return_to = "http://localhost:8080/callback";
+/// # let nonce_store = NoopNonceStore;
+/// let (req, verifier) =
+///     Verifier::from_querystring(&qs, expected_return_to, &nonce_store, ExpectedState::None).unwrap();
 /// // send off req, get back response
 /// # let response = Response;
 /// match verifier.verify_response(response.body()) {
@@ -24,27 +72,88 @@ use futures::{
 /// }
 /// # }
 /// ```
+///
+/// See [`Verifier::verify`] for a way to do this in one step, backed by any [`HttpClient`].
 pub struct Verifier {
-    claimed_id: u64,
+    claimed_id: SteamId,
 }
 
 impl Verifier {
     /// Constructs a Verifier and a HTTP request from a query string. You must use the method,
     /// headers, URI and body from the returned `http::Request` struct.
-    pub fn from_querystring<S: AsRef<str>>(s: S) -> Result<(http::Request<Vec<u8>>, Self), Error> {
+    ///
+    /// `expected_return_to` should be the value returned by
+    /// [`Redirector::return_to`](crate::Redirector::return_to) for the redirector that started
+    /// this login, so that a response cannot be replayed against a different callback. Responses
+    /// whose `response_nonce` timestamp falls outside the allowed clock skew are rejected, and
+    /// `nonce_store` is then consulted to reject a `response_nonce` that has already been passed to
+    /// this function once (it is recorded here, before the response is verified with Steam).
+    /// `expected_state` checks the CSRF/state token, if the redirector was created with one - see [`ExpectedState`].
+    pub fn from_querystring<S: AsRef<str>, N: NonceStore>(
+        s: S,
+        expected_return_to: &str,
+        nonce_store: &N,
+        expected_state: ExpectedState,
+    ) -> Result<(http::Request<Vec<u8>>, Self), Error> {
         let mut form: SteamAuthResponse =
             serde_urlencoded::from_str(s.as_ref()).map_err(Error::Deserialize)?;
 
+        if form.op_endpoint != STEAM_URL {
+            return Err(Error::AuthenticationFailed);
+        }
+
+        if form.return_to != expected_return_to {
+            return Err(Error::AuthenticationFailed);
+        }
+
+        match expected_state {
+            ExpectedState::None => {}
+            ExpectedState::Plain(expected) => {
+                if form.state.as_deref() != Some(expected) {
+                    return Err(Error::InvalidState);
+                }
+            }
+            ExpectedState::Signed(secret) => {
+                let provided = form.state.as_deref().ok_or(Error::InvalidState)?;
+                if !state::verify_signed_state(secret, provided) {
+                    return Err(Error::InvalidState);
+                }
+            }
+        }
+
+        let issued_at =
+            parse_nonce_timestamp(&form.response_nonce).ok_or(Error::AuthenticationFailed)?;
+        let skew = match issued_at.duration_since(SystemTime::now()) {
+            Ok(future_skew) => future_skew,
+            Err(e) => e.duration(),
+        };
+        if skew > ALLOWED_CLOCK_SKEW {
+            return Err(Error::AuthenticationFailed);
+        }
+
+        if !nonce_store.check_and_insert(&form.response_nonce, issued_at) {
+            return Err(Error::AuthenticationFailed);
+        }
+
         form.mode = "check_authentication".to_owned();
 
         let verifier = {
             let url = url::Url::parse(&form.claimed_id).map_err(|_| Error::ParseSteamId)?;
+
+            if url.host_str() != Some("steamcommunity.com")
+                || !url.path().starts_with("/openid/id/")
+            {
+                return Err(Error::AuthenticationFailed);
+            }
+
             let mut segments = url.path_segments().ok_or(Error::ParseSteamId)?;
             let id_segment = segments.next_back().ok_or(Error::ParseSteamId)?;
 
             let claimed_id = id_segment.parse::<u64>().map_err(|_| Error::ParseSteamId)?;
 
-            Self { claimed_id }
+            Self {
+                claimed_id: SteamId::from_u64(claimed_id),
+            }
         };
 
         let form_data = serde_urlencoded::to_string(form)
@@ -62,7 +171,7 @@ impl Verifier {
     }
 
     /// Verifies the response from the steam servers.
-    pub fn verify_response<S: Into<String>>(self, response_body: S) -> Result<u64, Error> {
+    pub fn verify_response<S: Into<String>>(self, response_body: S) -> Result<SteamId, Error> {
         let is_valid = response_body
             .into()
             .split('\n')
@@ -79,60 +188,24 @@ impl Verifier {
         }
     }
 
-    #[cfg(feature = "reqwest-09x")]
-    /// Constructs and sends a synchronous verification request. Requires the `reqwest-09x`
-    /// feature.
-    pub fn make_verify_request<S: AsRef<str>>(
-        client: &reqwest::Client,
+    /// Constructs a verification request, sends it with `client`, and checks the response, all in
+    /// one step. Works with any [`HttpClient`] implementation - enable the `reqwest` feature for
+    /// the provided [`ReqwestClient`](crate::ReqwestClient).
+    pub async fn verify<S: AsRef<str>, N: NonceStore, C: HttpClient>(
+        client: &C,
         querystring: S,
-    ) -> Result<u64, Error> {
-        let (req, verifier) = Self::from_querystring(querystring)?;
+        expected_return_to: &str,
+        nonce_store: &N,
+        expected_state: ExpectedState<'_>,
+    ) -> Result<SteamId, Error> {
+        let (req, verifier) =
+            Self::from_querystring(querystring, expected_return_to, nonce_store, expected_state)?;
 
         let (parts, body) = req.into_parts();
 
-        client
-            .post(&parts.uri.to_string())
-            .header("Content-Type", "application/x-www-form-urlencoded")
-            .body(body)
-            .send()
-            .map_err(Error::Reqwest)
-            .and_then(|mut response| {
-                let text = response.text().map_err(Error::Reqwest)?;
+        let text = client.post_form(&parts.uri.to_string(), body).await?;
 
-                verifier.verify_response(text)
-            })
-    }
-
-    #[cfg(feature = "reqwest-09x")]
-    /// Constructs and sends an asynchronous verification request. Requires the `reqwest-09x`
-    /// feature.
-    pub fn make_verify_request_async<S: AsRef<str>>(
-        client: &reqwest::r#async::Client,
-        querystring: S,
-    ) -> impl Future<Item = u64, Error = Error> {
-        let (req, verifier) = match Self::from_querystring(querystring) {
-            Ok(rv) => rv,
-            Err(e) => return Either::A(future::err(e)),
-        };
-
-        let (parts, body) = req.into_parts();
-
-        Either::B(
-            client
-                .post(&parts.uri.to_string())
-                .header("Content-Type", "application/x-www-form-urlencoded")
-                .body(body)
-                .send()
-                .map_err(Error::Reqwest)
-                .and_then(|res| res.into_body().concat2().map_err(Error::Reqwest))
-                .and_then(move |body| {
-                    let s = std::str::from_utf8(&body)
-                        .map_err(|_| Error::AuthenticationFailed)?
-                        .to_owned();
-
-                    verifier.verify_response(s)
-                }),
-        )
+        verifier.verify_response(text)
     }
 }
 
@@ -160,4 +233,101 @@ pub struct SteamAuthResponse {
     signed: String,
     #[serde(rename = "openid.sig")]
     sig: String,
+    #[serde(rename = "state", default, skip_serializing)]
+    state: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nonce::format_nonce_timestamp_for_test;
+    use std::sync::Mutex;
+
+    const EXPECTED_RETURN_TO: &str = "http://localhost:8080/callback";
+
+    #[derive(Default)]
+    struct TestNonceStore(Mutex<std::collections::HashSet<String>>);
+
+    impl NonceStore for TestNonceStore {
+        fn check_and_insert(&self, nonce: &str, _issued_at: SystemTime) -> bool {
+            self.0.lock().unwrap().insert(nonce.to_owned())
+        }
+    }
+
+    fn response_with_nonce(response_nonce: String) -> SteamAuthResponse {
+        SteamAuthResponse {
+            ns: "http://specs.openid.net/auth/2.0".to_owned(),
+            mode: "id_res".to_owned(),
+            op_endpoint: STEAM_URL.to_owned(),
+            claimed_id: "https://steamcommunity.com/openid/id/76561197960287930".to_owned(),
+            identity: Some("https://steamcommunity.com/openid/id/76561197960287930".to_owned()),
+            return_to: EXPECTED_RETURN_TO.to_owned(),
+            response_nonce,
+            invalidate_handle: None,
+            assoc_handle: "1234567890".to_owned(),
+            signed: "signed,op_endpoint,claimed_id,identity,return_to,response_nonce,assoc_handle"
+                .to_owned(),
+            sig: "BK0zC//KzERs7N+NlDO0aL06+BA=".to_owned(),
+            state: None,
+        }
+    }
+
+    #[test]
+    fn accepts_nonce_just_inside_skew_window() {
+        let nonce_store = TestNonceStore::default();
+        let issued_at = SystemTime::now() - Duration::from_secs(5 * 60 - 1);
+        let qs = serde_urlencoded::to_string(response_with_nonce(
+            format_nonce_timestamp_for_test(issued_at, "rest"),
+        ))
+        .unwrap();
+
+        let result =
+            Verifier::from_querystring(&qs, EXPECTED_RETURN_TO, &nonce_store, ExpectedState::None);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn rejects_nonce_outside_skew_window() {
+        let nonce_store = TestNonceStore::default();
+        let issued_at = SystemTime::now() - Duration::from_secs(5 * 60 + 30);
+        let qs = serde_urlencoded::to_string(response_with_nonce(
+            format_nonce_timestamp_for_test(issued_at, "rest"),
+        ))
+        .unwrap();
+
+        let result =
+            Verifier::from_querystring(&qs, EXPECTED_RETURN_TO, &nonce_store, ExpectedState::None);
+        assert!(matches!(result, Err(Error::AuthenticationFailed)));
+    }
+
+    #[test]
+    fn rejects_replayed_nonce() {
+        let nonce_store = TestNonceStore::default();
+        let nonce = format_nonce_timestamp_for_test(SystemTime::now(), "rest");
+        let qs = serde_urlencoded::to_string(response_with_nonce(nonce)).unwrap();
+
+        assert!(
+            Verifier::from_querystring(&qs, EXPECTED_RETURN_TO, &nonce_store, ExpectedState::None)
+                .is_ok()
+        );
+        assert!(matches!(
+            Verifier::from_querystring(&qs, EXPECTED_RETURN_TO, &nonce_store, ExpectedState::None),
+            Err(Error::AuthenticationFailed)
+        ));
+    }
+
+    #[test]
+    fn rejects_wrong_return_to() {
+        let nonce_store = TestNonceStore::default();
+        let nonce = format_nonce_timestamp_for_test(SystemTime::now(), "rest");
+        let qs = serde_urlencoded::to_string(response_with_nonce(nonce)).unwrap();
+
+        let result = Verifier::from_querystring(
+            &qs,
+            "http://localhost:8080/other",
+            &nonce_store,
+            ExpectedState::None,
+        );
+        assert!(matches!(result, Err(Error::AuthenticationFailed)));
+    }
 }
@@ -0,0 +1,173 @@
+//! A small async client for the parts of the Steam Web API useful after a login: resolving
+//! vanity URLs and fetching public profile summaries.
+//!
+//! Requires a Web API key, obtainable from <https://steamcommunity.com/dev/apikey>.
+
+use crate::{Error, HttpClient, SteamId};
+
+const RESOLVE_VANITY_URL: &str = "https://api.steampowered.com/ISteamUser/ResolveVanityURL/v0001/";
+const GET_PLAYER_SUMMARIES: &str =
+    "https://api.steampowered.com/ISteamUser/GetPlayerSummaries/v0002/";
+
+/// A client for the Steam Web API, authenticated with a Web API key.
+///
+/// Doesn't hold an HTTP client of its own - pass any [`HttpClient`] implementation to each call,
+/// the same one you already pass to [`Verifier::verify`](crate::Verifier::verify).
+///
+/// # Example
+/// ```no_run
+/// # use steam_auth::WebApiClient;
+/// # fn main() {
+/// let client = WebApiClient::new("MY_WEB_API_KEY");
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct WebApiClient {
+    key: String,
+}
+
+impl WebApiClient {
+    /// Constructs a client from a Web API key.
+    pub fn new<S: Into<String>>(key: S) -> Self {
+        Self { key: key.into() }
+    }
+
+    /// Resolves a vanity URL (the part of `steamcommunity.com/id/<vanityurl>`) to a [`SteamId`].
+    /// Returns `None` if no account has claimed that vanity URL.
+    pub async fn resolve_vanity_url<C: HttpClient>(
+        &self,
+        client: &C,
+        vanity_url: &str,
+    ) -> Result<Option<SteamId>, Error> {
+        let text = client
+            .get(
+                RESOLVE_VANITY_URL,
+                &[("key", self.key.as_str()), ("vanityurl", vanity_url)],
+            )
+            .await?;
+        let resp: ResolveVanityUrlResponse =
+            serde_json::from_str(&text).map_err(Error::DeserializeJson)?;
+
+        Ok(match resp.response.success {
+            1 => resp
+                .response
+                .steamid
+                .and_then(|s| s.parse::<u64>().ok())
+                .map(SteamId::from_u64),
+            _ => None,
+        })
+    }
+
+    /// Fetches the public profile summary for a [`SteamId`]. Returns `None` if no such account
+    /// exists.
+    pub async fn get_player_summary<C: HttpClient>(
+        &self,
+        client: &C,
+        steam_id: SteamId,
+    ) -> Result<Option<PlayerSummary>, Error> {
+        let text = client
+            .get(
+                GET_PLAYER_SUMMARIES,
+                &[
+                    ("key", self.key.as_str()),
+                    ("steamids", steam_id.as_u64().to_string().as_str()),
+                ],
+            )
+            .await?;
+        let resp: PlayerSummariesResponse =
+            serde_json::from_str(&text).map_err(Error::DeserializeJson)?;
+
+        Ok(resp.response.players.into_iter().next().map(Into::into))
+    }
+}
+
+/// The online state of a Steam user, as reported by `GetPlayerSummaries`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnlineState {
+    Offline,
+    Online,
+    Busy,
+    Away,
+    Snooze,
+    LookingToTrade,
+    LookingToPlay,
+    /// A `personastate` value not recognised by this crate.
+    Unknown(u8),
+}
+
+impl OnlineState {
+    fn from_u8(n: u8) -> Self {
+        match n {
+            0 => OnlineState::Offline,
+            1 => OnlineState::Online,
+            2 => OnlineState::Busy,
+            3 => OnlineState::Away,
+            4 => OnlineState::Snooze,
+            5 => OnlineState::LookingToTrade,
+            6 => OnlineState::LookingToPlay,
+            n => OnlineState::Unknown(n),
+        }
+    }
+}
+
+/// A public profile summary, as returned by `GetPlayerSummaries`.
+#[derive(Debug, Clone)]
+pub struct PlayerSummary {
+    pub steam_id: SteamId,
+    pub persona_name: String,
+    pub profile_url: String,
+    pub avatar: String,
+    pub avatar_medium: String,
+    pub avatar_full: String,
+    pub online_state: OnlineState,
+}
+
+impl From<RawPlayerSummary> for PlayerSummary {
+    fn from(raw: RawPlayerSummary) -> Self {
+        Self {
+            steam_id: raw
+                .steamid
+                .parse::<u64>()
+                .map(SteamId::from_u64)
+                .unwrap_or_else(|_| SteamId::from_u64(0)),
+            persona_name: raw.personaname,
+            profile_url: raw.profileurl,
+            avatar: raw.avatar,
+            avatar_medium: raw.avatarmedium,
+            avatar_full: raw.avatarfull,
+            online_state: OnlineState::from_u8(raw.personastate),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ResolveVanityUrlResponse {
+    response: ResolveVanityUrlInner,
+}
+
+#[derive(Deserialize)]
+struct ResolveVanityUrlInner {
+    steamid: Option<String>,
+    success: i32,
+}
+
+#[derive(Deserialize)]
+struct PlayerSummariesResponse {
+    response: PlayerSummariesInner,
+}
+
+#[derive(Deserialize)]
+struct PlayerSummariesInner {
+    players: Vec<RawPlayerSummary>,
+}
+
+#[derive(Deserialize)]
+struct RawPlayerSummary {
+    steamid: String,
+    personaname: String,
+    profileurl: String,
+    avatar: String,
+    avatarmedium: String,
+    avatarfull: String,
+    personastate: u8,
+}